@@ -0,0 +1,169 @@
+use bevy::input::mouse::{MouseMotion, MouseWheel};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::DragTarget;
+
+/// A camera that orbits around and pans `focus`, zooming by adjusting `radius`.
+#[derive(Component)]
+pub struct PanOrbitCamera {
+    pub focus: Vec3,
+    pub radius: f32,
+    pub upside_down: bool,
+}
+
+impl Default for PanOrbitCamera {
+    fn default() -> Self {
+        PanOrbitCamera {
+            focus: Vec3::ZERO,
+            radius: 5.0,
+            upside_down: false,
+        }
+    }
+}
+
+/// Mouse input accumulated this frame, consumed by `update_camera_system`.
+#[derive(Resource, Default)]
+pub struct MouseEvents {
+    orbit: Vec2,
+    pan: Vec2,
+    zoom: f32,
+}
+
+pub fn accumulate_mouse_events_system(
+    mut motion_events: EventReader<MouseMotion>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse_events: ResMut<MouseEvents>,
+) {
+    mouse_events.orbit = Vec2::ZERO;
+    mouse_events.pan = Vec2::ZERO;
+    mouse_events.zoom = 0.0;
+
+    for motion in motion_events.iter() {
+        if mouse_buttons.pressed(MouseButton::Right) {
+            mouse_events.orbit += motion.delta;
+        } else if mouse_buttons.pressed(MouseButton::Middle) {
+            mouse_events.pan += motion.delta;
+        }
+    }
+    for wheel in wheel_events.iter() {
+        mouse_events.zoom += wheel.y;
+    }
+}
+
+pub fn update_camera_system(
+    mouse_events: Res<MouseEvents>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut PanOrbitCamera, &mut Transform, &Projection)>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+
+    for (mut pan_orbit, mut transform, projection) in &mut cameras {
+        if mouse_events.orbit.length_squared() > 0.0 {
+            let upside_down = pan_orbit.upside_down;
+            let sign = if upside_down { -1.0 } else { 1.0 };
+            let delta_x = mouse_events.orbit.x / window_size.x * std::f32::consts::TAU * sign;
+            let delta_y = mouse_events.orbit.y / window_size.y * std::f32::consts::PI;
+            let yaw = Quat::from_rotation_y(-delta_x);
+            let pitch = Quat::from_rotation_x(-delta_y);
+            transform.rotation = yaw * transform.rotation * pitch;
+
+            let up = transform.rotation * Vec3::Y;
+            pan_orbit.upside_down = up.y <= 0.0;
+        } else if mouse_events.pan.length_squared() > 0.0 {
+            let mut pan = mouse_events.pan;
+            if let Projection::Perspective(projection) = projection {
+                pan *= Vec2::new(projection.fov * projection.aspect_ratio, projection.fov) / window_size;
+            }
+            let right = transform.rotation * Vec3::X * -pan.x;
+            let up = transform.rotation * Vec3::Y * pan.y;
+            let translation = (right + up) * pan_orbit.radius;
+            pan_orbit.focus += translation;
+        } else if mouse_events.zoom.abs() > 0.0 {
+            pan_orbit.radius -= mouse_events.zoom * pan_orbit.radius * 0.2;
+            pan_orbit.radius = f32::max(pan_orbit.radius, 0.05);
+        }
+
+        let rotation = transform.rotation;
+        transform.translation = pan_orbit.focus + rotation * Vec3::new(0.0, 0.0, pan_orbit.radius);
+    }
+}
+
+/// Rotation-around, distance and altitude offsets used to frame the body
+/// currently being dragged, plus how quickly the camera eases towards them.
+/// Mirrors cyber_rider's `DebugCamOffset { rot, dist, alt }`.
+#[derive(Resource)]
+pub struct CameraFollowSettings {
+    pub rot: f32,
+    pub dist: f32,
+    pub alt: f32,
+    pub smoothing: f32,
+}
+
+impl Default for CameraFollowSettings {
+    fn default() -> Self {
+        Self {
+            rot: 0.0,
+            dist: 0.5,
+            alt: 0.2,
+            smoothing: 0.1,
+        }
+    }
+}
+
+/// The manual framing a camera had right before it started following a
+/// dragged body, so `camera_follow_system` can restore it on `DragEnd`.
+#[derive(Clone, Copy)]
+struct PreFollowFraming {
+    focus: Vec3,
+    radius: f32,
+    rotation: Quat,
+}
+
+/// While a body is being dragged, eases the camera's focus towards it and its
+/// radius towards `CameraFollowSettings::dist` so the grabbed object stays
+/// framed. Once no body is being dragged any more (after the last `DragEnd`),
+/// the camera's pre-drag focus/radius/rotation are restored, so manual
+/// pan/orbit control resumes from wherever the user had actually left it.
+pub fn camera_follow_system(
+    settings: Res<CameraFollowSettings>,
+    dragged: Query<&GlobalTransform, With<DragTarget>>,
+    mut cameras: Query<(&mut PanOrbitCamera, &mut Transform)>,
+    mut pre_follow: Local<Option<PreFollowFraming>>,
+) {
+    // multiple bodies can be dragged at once (see `DragPlugin`); pick the
+    // first deterministically rather than requiring exactly one `DragTarget`
+    let Some(dragged_transform) = dragged.iter().next() else {
+        if let Some(framing) = pre_follow.take() {
+            for (mut pan_orbit, mut transform) in &mut cameras {
+                pan_orbit.focus = framing.focus;
+                pan_orbit.radius = framing.radius;
+                transform.rotation = framing.rotation;
+            }
+        }
+        return;
+    };
+    let target_focus = dragged_transform.translation();
+
+    for (mut pan_orbit, mut transform) in &mut cameras {
+        if pre_follow.is_none() {
+            *pre_follow = Some(PreFollowFraming {
+                focus: pan_orbit.focus,
+                radius: pan_orbit.radius,
+                rotation: transform.rotation,
+            });
+        }
+
+        pan_orbit.focus = pan_orbit.focus.lerp(target_focus, settings.smoothing);
+        pan_orbit.radius += (settings.dist - pan_orbit.radius) * settings.smoothing;
+
+        let offset = Quat::from_rotation_y(settings.rot) * Vec3::new(0.0, settings.alt, pan_orbit.radius);
+        let desired_translation = pan_orbit.focus + offset;
+        transform.translation = transform.translation.lerp(desired_translation, settings.smoothing);
+        transform.look_at(pan_orbit.focus, Vec3::Y);
+    }
+}
@@ -1,5 +1,7 @@
 mod camera;
 
+use std::collections::HashMap;
+
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use bevy_mod_picking::prelude::*;
@@ -11,19 +13,140 @@ fn render_origin(mut gizmos: Gizmos) {
     gizmos.line(Vec3::ZERO, Vec3::Z, Color::BLUE);
 }
 
+/// How a `DragTarget` drives its body towards the drag point.
+#[derive(Clone, Copy)]
+enum DragMode {
+    /// drive the body directly with a clamped impulse (see `drag_system`)
+    Impulse,
+    /// attach an invisible kinematic cursor body to the grab point via a
+    /// spring-like joint and let the physics solver do the rest
+    Joint { stiffness: f32, damping: f32 },
+}
+
+/// The plane a drag target is solved against, defined by a point (the grab
+/// origin stored on `DragTarget`) and this normal.
+#[derive(Clone, Copy)]
+enum DragPlane {
+    /// normal = Y, i.e. the current ground-plane behaviour
+    Horizontal,
+    /// normal = camera forward, so the object can be lifted off the floor
+    /// and dragged towards/away from the camera
+    CameraFacing,
+    Custom(Vec3),
+}
+
+impl DragPlane {
+    fn normal(self, camera_transform: &GlobalTransform) -> Vec3 {
+        match self {
+            DragPlane::Horizontal => Vec3::Y,
+            DragPlane::CameraFacing => camera_transform.forward(),
+            DragPlane::Custom(normal) => normal,
+        }
+    }
+}
+
+/// Global default for how newly started drags behave. Swap `mode` to try
+/// the joint-based dragging instead of the hand-tuned impulse path.
+#[derive(Resource)]
+struct DragSettings {
+    mode: DragMode,
+    plane: DragPlane,
+}
+
+impl Default for DragSettings {
+    fn default() -> Self {
+        Self {
+            mode: DragMode::Impulse,
+            plane: DragPlane::Horizontal,
+        }
+    }
+}
+
+/// Marker for the invisible kinematic body used to anchor a `DragMode::Joint` drag.
+#[derive(Component)]
+struct DragCursor;
+
+/// Tunable gains for the PID controller driving `DragMode::Impulse`.
+/// Mirrors cyber_rider's `MovementSettings` pattern so the response can be
+/// tuned without recompiling.
+#[derive(Resource)]
+struct DragControlSettings {
+    kp: f32,
+    ki: f32,
+    kd: f32,
+    // per-axis clamp on the accumulated integral term (anti-windup)
+    integral_limit: f32,
+    max_impulse: f32,
+}
+
+impl Default for DragControlSettings {
+    fn default() -> Self {
+        Self {
+            kp: 1.5,
+            ki: 0.3,
+            kd: 0.15,
+            integral_limit: 1.0,
+            max_impulse: 1.0,
+        }
+    }
+}
+
+/// Snapshot of a dragged body's position/velocity from the previous frame,
+/// used by `anti_tunneling_system` to sweep-test for missed collisions.
+#[derive(Component, Default)]
+struct PreviousVelocity {
+    translation: Vec3,
+    linvel: Vec3,
+}
+
+/// Marks a body that was just detected tunneling through a collider.
+/// `tunneling_recovery_system` nudges it back along `dir` until `frames` runs out.
 #[derive(Component)]
-struct DragTarget {
+struct Tunneling {
+    frames: u8,
+    dir: Vec3,
+}
+
+/// Marks an entity that can be picked up by `DragPlugin`. Any entity with
+/// this component (and a `PickableBundle`) gets the drag handlers wired up
+/// automatically, instead of the demo hand-registering them on one box.
+/// `drag_system` only drives bodies that also have `ExternalImpulse` and
+/// `ReadMassProperties`, and `anti_tunneling_system` needs a `Velocity` component.
+#[derive(Component)]
+struct Draggable;
+
+// `pub(crate)` so `camera::camera_follow_system` can key off `With<DragTarget>`
+// to find the body currently being dragged.
+#[derive(Component)]
+pub(crate) struct DragTarget {
     // the camera on which this drag is occuring
     camera: Entity,
 
+    // the pointer driving this drag, so multiple pointers can each drag
+    // their own body concurrently
+    pointer: PointerId,
+
     // allows calculating the drag target from the mouse
     origin: Vec3,
 
     // the offset from the center of mass where the drag started
     offset: Vec3,
 
-    // distance of the drag (as last reported by events<pointer<drag>>)
-    distance: Vec2,
+    // the pointer's last-known viewport position (as reported by events<pointer<drag>>)
+    pointer_position: Vec2,
+
+    // how this drag is being driven
+    mode: DragMode,
+
+    // the plane the pointer ray is intersected against
+    plane: DragPlane,
+
+    // the kinematic cursor body (and joint) spawned for `DragMode::Joint`
+    cursor: Option<Entity>,
+
+    // running PID state for `DragMode::Impulse`, reset on `DragStart`
+    integral: Vec3,
+    prev_error: Vec3,
 }
 
 pub fn main() {
@@ -45,13 +168,124 @@ pub fn main() {
         .add_plugins(DefaultPickingPlugins)
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
         //.add_plugins(RapierDebugRenderPlugin::default())
+        .add_plugins(DragPlugin)
+        .init_resource::<camera::MouseEvents>()
+        .init_resource::<camera::CameraFollowSettings>()
         .add_systems(Startup, setup)
         .add_systems(Update, render_origin)
-        .add_systems(Update, (camera::update_camera_system, camera::accumulate_mouse_events_system))
-        .add_systems(Update, drag_system)
+        .add_systems(
+            Update,
+            (
+                camera::accumulate_mouse_events_system,
+                camera::update_camera_system,
+                camera::camera_follow_system,
+            )
+                .chain(),
+        )
         .run();
 }
 
+/// Drop-in dragging subsystem: wires up drag handlers on any `Draggable`
+/// entity, drives all active drags, and guards them against tunneling.
+struct DragPlugin;
+
+impl Plugin for DragPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DragSettings>()
+            .init_resource::<DragControlSettings>()
+            .add_systems(
+                Update,
+                (
+                    register_draggable_system,
+                    drag_system,
+                    anti_tunneling_system,
+                    tunneling_recovery_system,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Wires the drag handlers onto newly added `Draggable` entities, so callers
+/// only need to insert the marker instead of repeating the `On::<Pointer<_>>` boilerplate.
+fn register_draggable_system(
+    mut commands: Commands,
+    added: Query<Entity, Added<Draggable>>,
+) {
+    for entity in &added {
+        commands.entity(entity).insert((
+            On::<Pointer<DragStart>>::run(on_drag_start),
+            On::<Pointer<DragEnd>>::run(on_drag_end),
+        ));
+    }
+}
+
+fn on_drag_start(
+    listener: Listener<Pointer<DragStart>>,
+    targets: Query<&GlobalTransform, With<Draggable>>,
+    drag_settings: Res<DragSettings>,
+    mut commands: Commands,
+) {
+    if listener.button != PointerButton::Primary {
+        return;
+    }
+    let Ok(target_transform) = targets.get(listener.target()) else {
+        return;
+    };
+    let position = listener.hit.position
+        .expect("backend does not support `position`");
+    let offset = target_transform.affine().inverse().transform_point3(position);
+    let cursor = match drag_settings.mode {
+        DragMode::Impulse => None,
+        DragMode::Joint { stiffness, damping } => {
+            // `ImpulseJoint::new(parent, data)` below makes the box body1 and
+            // the cursor body2, so anchor1 is box-local and anchor2 is
+            // cursor-local; the cursor is spawned at the world grab point,
+            // so its local anchor is the origin.
+            let joint = GenericJointBuilder::new(JointAxesMask::empty())
+                .local_anchor1(offset)
+                .local_anchor2(Vec3::ZERO)
+                .motor_position(JointAxis::X, 0.0, stiffness, damping)
+                .motor_position(JointAxis::Y, 0.0, stiffness, damping)
+                .motor_position(JointAxis::Z, 0.0, stiffness, damping)
+                .build();
+            Some(commands
+                .spawn((
+                    DragCursor,
+                    RigidBody::KinematicPositionBased,
+                    TransformBundle::from_transform(Transform::from_translation(position)),
+                    ImpulseJoint::new(listener.target(), joint),
+                ))
+                .id())
+        }
+    };
+    commands.entity(listener.target()).insert(DragTarget {
+        camera: listener.hit.camera,
+        pointer: listener.pointer_id,
+        origin: position,
+        offset,
+        pointer_position: listener.pointer_location.position,
+        mode: drag_settings.mode,
+        plane: drag_settings.plane,
+        cursor,
+        integral: Vec3::ZERO,
+        prev_error: Vec3::ZERO,
+    });
+}
+
+fn on_drag_end(
+    listener: Listener<Pointer<DragEnd>>,
+    targets: Query<&DragTarget>,
+    mut commands: Commands,
+) {
+    if let Ok(drag_target) = targets.get(listener.target()) {
+        if let Some(cursor) = drag_target.cursor {
+            commands.entity(cursor).despawn();
+        }
+    }
+    commands.entity(listener.target()).remove::<DragTarget>();
+}
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -104,6 +338,8 @@ fn setup(
     commands
         .spawn((Collider::cuboid(0.05, 0.05, 0.05), RigidBody::Dynamic))
         .insert(ColliderMassProperties::Mass(1.0))
+        .insert(ReadMassProperties::default())
+        .insert(Velocity::default())
         .insert(SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.05, 0.0)))
         .with_children(|commands| {
             commands.spawn(PbrBundle {
@@ -114,69 +350,163 @@ fn setup(
         })
         .insert(ExternalImpulse::default())
         .insert(PickableBundle::default())
-        // DRAG START
-        .insert(On::<Pointer<DragStart>>::run(|
-            listener: Listener<Pointer<DragStart>>,
-            target: Query<&GlobalTransform, With<ExternalImpulse>>,
-            mut commands: Commands| {
-            if listener.button == PointerButton::Primary {
-                let target_transform = target.get_single().unwrap();
-                let position = listener.hit.position
-                    .expect("backend does not support `position`");
-                commands.entity(listener.target()).insert(DragTarget {
-                    camera: listener.hit.camera,
-                    origin: position,
-                    offset: target_transform.affine().inverse().transform_point3(position),
-                    distance: Default::default()
-                });
-            }
-        }))
-        // DRAG END
-        .insert(On::<Pointer<DragEnd>>::target_remove::<DragTarget>());
+        .insert(Draggable);
 }
 
 fn drag_system(
+    time: Res<Time>,
+    rapier_config: Res<RapierConfiguration>,
+    drag_control: Res<DragControlSettings>,
     mut drag_events: EventReader<Pointer<Drag>>,
-    mut target: Query<(&mut DragTarget, &GlobalTransform, &mut ExternalImpulse)>,
-    camera_transforms: Query<&GlobalTransform, With<Camera>>,
+    mut targets: Query<(&mut DragTarget, &GlobalTransform, &mut ExternalImpulse, &ReadMassProperties)>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut cursors: Query<&mut Transform, With<DragCursor>>,
 ) {
-    if let Ok((mut target, target_transform, mut target_force)) = target.get_single_mut() {
-        /* update the cached target distance */
-        if let Some(last_drag_event) = drag_events.iter().last() {
-            target.distance = last_drag_event.distance;
+    /* one pointer may only be dragging a single body at a time, so the last
+       event per pointer this frame is the body's current pointer position */
+    let mut pointer_positions: HashMap<PointerId, Vec2> = HashMap::new();
+    for drag_event in drag_events.iter() {
+        pointer_positions.insert(drag_event.pointer_id, drag_event.pointer_location.position);
+    }
+
+    for (mut target, target_transform, mut target_force, mass_properties) in &mut targets {
+        if let Some(pointer_position) = pointer_positions.get(&target.pointer) {
+            target.pointer_position = *pointer_position;
         }
 
-        /* convert drag target distance  */
-        let camera_transform = camera_transforms
-            .get(target.camera)
-            .unwrap();
-        let mut drag_target_offset = camera_transform.translation() +
-            target.distance.x * camera_transform.right() -
-            target.distance.y * camera_transform.up();
-        drag_target_offset.y = 0.0;
-
-        // TODO: improve zoom factor for lower camera altitudes
-        let zoom_factor = (camera_transform.translation() - target.origin).length() * 0.0011;
-        let drag_target = target.origin + (drag_target_offset * zoom_factor);
-        let drag_point = target_transform.transform_point(target.offset);
-
-        // TODO: make gain a factor of object weight
-        const GAIN: f32 = 1.5;
-        // TODO: use PID control?
-        let drag_impulse = (drag_target - drag_point)
-            .clamp(Vec3::NEG_ONE, Vec3::ONE) * GAIN;
-        target_force.impulse = drag_impulse;
-
-        let mut drag_com_offset = drag_point - target_transform.translation();
-        drag_com_offset.y = 0.0;
-
-        let orthogonal_vector = (drag_com_offset) - (drag_com_offset).project_onto(drag_impulse);
-        target_force.torque_impulse = orthogonal_vector.cross(drag_impulse);
-    }
-}
+        /* solve the drag target by intersecting the pointer ray with the drag plane */
+        // the camera can be despawned mid-drag too (e.g. a scene change);
+        // skip this target for a frame rather than panicking
+        let Ok((camera, camera_transform)) = cameras.get(target.camera) else {
+            continue;
+        };
+        // the pointer can be outside the viewport (or the ray otherwise
+        // degenerate) while drag events are still in flight; just leave this
+        // target where it was last frame rather than panicking
+        let Some(ray) = camera.viewport_to_world(camera_transform, target.pointer_position) else {
+            continue;
+        };
+        let normal = target.plane.normal(camera_transform);
+        let denominator = ray.direction.dot(normal);
+        let drag_target = if denominator.abs() > f32::EPSILON {
+            let distance = (target.origin - ray.origin).dot(normal) / denominator;
+            ray.origin + ray.direction * distance
+        } else {
+            target.origin
+        };
+
+        match target.mode {
+            DragMode::Impulse => {
+                let drag_point = target_transform.transform_point(target.offset);
+                // PID gains are tuned against the physics step, not the render
+                // frame, so the response stays stable regardless of frame rate
+                let dt = match rapier_config.timestep_mode {
+                    TimestepMode::Fixed { dt, .. } => dt,
+                    TimestepMode::Interpolated { dt, .. } => dt,
+                    // `max_dt` is only a cap on the step Rapier will take, not
+                    // the step actually taken this frame; fall back to the
+                    // real frame delta, clamped to that cap
+                    TimestepMode::Variable { max_dt, .. } => time.delta_seconds().min(max_dt),
+                };
+                // scale control effort by mass so light and heavy bodies track identically
+                let mass = mass_properties.0.mass.max(f32::EPSILON);
 
+                let error = drag_target - drag_point;
+                target.integral = (target.integral + error * dt)
+                    .clamp(Vec3::NEG_ONE * drag_control.integral_limit, Vec3::ONE * drag_control.integral_limit);
+                let derivative = if dt > 0.0 { (error - target.prev_error) / dt } else { Vec3::ZERO };
+                target.prev_error = error;
 
+                let drag_impulse = ((drag_control.kp * error
+                    + drag_control.ki * target.integral
+                    + drag_control.kd * derivative)
+                    * mass)
+                    .clamp_length_max(drag_control.max_impulse * mass);
+                target_force.impulse = drag_impulse;
 
+                let mut drag_com_offset = drag_point - target_transform.translation();
+                drag_com_offset.y = 0.0;
 
+                let orthogonal_vector = (drag_com_offset) - (drag_com_offset).project_onto(drag_impulse);
+                target_force.torque_impulse = orthogonal_vector.cross(drag_impulse);
+            }
+            DragMode::Joint { .. } => {
+                if let Some(cursor) = target.cursor {
+                    if let Ok(mut cursor_transform) = cursors.get_mut(cursor) {
+                        cursor_transform.translation = drag_target;
+                    }
+                }
+            }
+        }
+    }
+}
 
+/// While a body is being dragged it can be flung fast enough to skip clean
+/// through thin colliders in a single step. Enable CCD for the duration of
+/// the drag and sweep-test each frame's motion against the query pipeline so
+/// a missed collision can be flagged for `tunneling_recovery_system`.
+fn anti_tunneling_system(
+    time: Res<Time>,
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    added: Query<(Entity, &GlobalTransform), Added<DragTarget>>,
+    mut removed: RemovedComponents<DragTarget>,
+    mut bodies: Query<(Entity, &GlobalTransform, &Velocity, &mut PreviousVelocity), With<DragTarget>>,
+) {
+    for (entity, transform) in &added {
+        commands.entity(entity).insert((
+            Ccd::enabled(),
+            PreviousVelocity {
+                translation: transform.translation(),
+                linvel: Vec3::ZERO,
+            },
+        ));
+    }
+    for entity in removed.iter() {
+        commands.entity(entity).remove::<(Ccd, PreviousVelocity)>();
+    }
 
+    for (entity, transform, velocity, mut previous) in &mut bodies {
+        let current = transform.translation();
+        // `GlobalTransform` can lag a fast-moving body by a frame (e.g. right
+        // after `DragStart`), so fall back to the last recorded velocity to
+        // still get a meaningful sweep direction/length
+        let mut swept = current - previous.translation;
+        if swept.length() <= f32::EPSILON {
+            swept = previous.linvel * time.delta_seconds();
+        }
+        let distance = swept.length();
+        if distance > f32::EPSILON {
+            if let Some((_, intersection)) = rapier_context.cast_ray_and_normal(
+                previous.translation,
+                swept,
+                1.0,
+                true,
+                QueryFilter::default().exclude_rigid_body(entity),
+            ) {
+                commands.entity(entity).insert(Tunneling {
+                    frames: 15,
+                    dir: intersection.normal,
+                });
+            }
+        }
+        previous.translation = current;
+        previous.linvel = velocity.linvel;
+    }
+}
+
+/// Pulls a body that just tunneled through geometry back onto the correct
+/// side with a small corrective impulse each frame until `frames` runs out.
+fn tunneling_recovery_system(
+    mut commands: Commands,
+    mut bodies: Query<(Entity, &mut Tunneling, &mut ExternalImpulse)>,
+) {
+    const RECOVERY_IMPULSE: f32 = 0.05;
+    for (entity, mut tunneling, mut impulse) in &mut bodies {
+        impulse.impulse += tunneling.dir * RECOVERY_IMPULSE;
+        tunneling.frames -= 1;
+        if tunneling.frames == 0 {
+            commands.entity(entity).remove::<Tunneling>();
+        }
+    }
+}